@@ -0,0 +1,58 @@
+//! This example shows how to resolve [ResourceRecord]s asynchronously using [PkarrClientAsync].
+//!
+//! run this example from the project root:
+//!     $ cargo run --example resolve_async <zbase32 encoded key>
+
+use tracing::Level;
+use tracing_subscriber;
+
+use std::time::Instant;
+
+use pkarr::{PkarrClientAsync, PublicKey};
+
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Mutable data public key.
+    public_key: String,
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_max_level(Level::DEBUG)
+        .with_env_filter("pkarr")
+        .init();
+
+    let cli = Cli::parse();
+
+    let public_key: PublicKey = cli
+        .public_key
+        .as_str()
+        .try_into()
+        .expect("Invalid zbase32 encoded key");
+
+    let client = PkarrClientAsync::builder().build().unwrap();
+
+    println!("Resolving Pkarr: {} ...", cli.public_key);
+
+    let start = Instant::now();
+
+    match client.resolve(&public_key).await {
+        Ok(Some(signed_packet)) => {
+            println!(
+                "\nResolved in {:?} milliseconds {}",
+                start.elapsed().as_millis(),
+                signed_packet
+            );
+        }
+        Ok(None) => {
+            println!("\nFailed to resolve {}", public_key);
+        }
+        Err(error) => {
+            println!("Got error: {:?}", error)
+        }
+    }
+}
@@ -0,0 +1,723 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bytes::Bytes;
+use mainline::Dht;
+use tracing::debug;
+use url::Url;
+use x25519_dalek::PublicKey as X25519PublicKey;
+
+use crate::{
+    bep44::Bep44Args,
+    cache::{HeedPkarrCache, PkarrCache},
+    merkle::{self, TreeHeadCache},
+    transport::{ObfuscatedTransport, Transport},
+    Error, PublicKey, Result, SignedPacket, DEFAULT_MAXIMUM_TTL, DEFAULT_MINIMUM_TTL,
+};
+
+/// Builder for [PkarrClientAsync], mirroring [crate::client::PkarrClientBuilder] but producing a
+/// non-blocking client.
+#[derive(Debug, Default)]
+pub struct PkarrClientAsyncBuilder {
+    relays: Option<Vec<Url>>,
+    cache: Option<HeedPkarrCache>,
+    minimum_ttl: Option<u32>,
+    maximum_ttl: Option<u32>,
+    obfuscated_transport_keys: HashMap<Url, X25519PublicKey>,
+    merkle_verify_keys: HashMap<Url, PublicKey>,
+}
+
+impl PkarrClientAsyncBuilder {
+    /// Set the relays used to publish/resolve [SignedPacket]s over HTTP, in addition to the
+    /// Mainline DHT.
+    pub fn relays(mut self, relays: Vec<Url>) -> Self {
+        self.relays = Some(relays);
+        self
+    }
+
+    /// Use a specific [HeedPkarrCache] instead of the default on-disk cache.
+    pub fn cache(mut self, cache: HeedPkarrCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Set the minimum TTL for cached [SignedPacket]s, in seconds.
+    pub fn minimum_ttl(mut self, ttl: u32) -> Self {
+        self.minimum_ttl = Some(ttl);
+        self
+    }
+
+    /// Set the maximum TTL for cached [SignedPacket]s, in seconds.
+    pub fn maximum_ttl(mut self, ttl: u32) -> Self {
+        self.maximum_ttl = Some(ttl);
+        self
+    }
+
+    /// Carry the payloads exchanged with `relay` inside the obfs4-style [ObfuscatedTransport],
+    /// using `server_public_key` as that relay's long-term identity. Only the HTTP request and
+    /// response bodies are obfuscated: the request is still plain HTTP, and its URL path names
+    /// the record's public key.
+    ///
+    /// Each relay has its own identity, so this is called once per obfuscated relay rather than
+    /// taking a single key for every relay in [PkarrClientAsyncBuilder::relays].
+    pub fn obfuscated_transport(mut self, relay: Url, server_public_key: X25519PublicKey) -> Self {
+        self.obfuscated_transport_keys
+            .insert(relay, server_public_key);
+        self
+    }
+
+    /// Require and verify a [merkle::InclusionProof] (and, once cached, a
+    /// [merkle::ConsistencyProof]) on every [PkarrClientAsync::resolve] through `relay`, against
+    /// tree heads signed by `relay_public_key`. This is what lets a client detect `relay` serving
+    /// a stale record or a different history to different clients.
+    pub fn merkle_verification(mut self, relay: Url, relay_public_key: PublicKey) -> Self {
+        self.merkle_verify_keys.insert(relay, relay_public_key);
+        self
+    }
+
+    /// Build the [PkarrClientAsync].
+    pub fn build(self) -> Result<PkarrClientAsync> {
+        Ok(PkarrClientAsync {
+            http_client: reqwest::Client::new(),
+            dht: Dht::client()?,
+            relays: self.relays,
+            cache: Arc::new(self.cache.unwrap_or_default()),
+            minimum_ttl: self.minimum_ttl.unwrap_or(DEFAULT_MINIMUM_TTL),
+            maximum_ttl: self.maximum_ttl.unwrap_or(DEFAULT_MAXIMUM_TTL),
+            obfuscated_transport_keys: self.obfuscated_transport_keys,
+            merkle_verify_keys: self.merkle_verify_keys,
+            tree_head_cache: Arc::new(std::sync::Mutex::new(TreeHeadCache::new())),
+        })
+    }
+}
+
+/// An async (tokio) counterpart to [crate::PkarrClient].
+///
+/// Built on non-blocking [reqwest] requests to the configured relays and the async flavor of
+/// the Mainline DHT Rpc, so thousands of concurrent [PkarrClientAsync::resolve] calls can run on
+/// a handful of tokio tasks instead of a thread per lookup. It shares the same [SignedPacket],
+/// [Bep44Args], and [HeedPkarrCache] types as the blocking [crate::PkarrClient].
+#[derive(Clone, Debug)]
+pub struct PkarrClientAsync {
+    http_client: reqwest::Client,
+    dht: Dht,
+    relays: Option<Vec<Url>>,
+    cache: Arc<HeedPkarrCache>,
+    minimum_ttl: u32,
+    maximum_ttl: u32,
+    obfuscated_transport_keys: HashMap<Url, X25519PublicKey>,
+    merkle_verify_keys: HashMap<Url, PublicKey>,
+    /// The last [merkle::TreeHead] seen from each relay in [merkle_verify_keys], so every
+    /// subsequent resolve can demand a [merkle::ConsistencyProof] against it.
+    tree_head_cache: Arc<std::sync::Mutex<TreeHeadCache>>,
+}
+
+impl PkarrClientAsync {
+    /// Create a new [PkarrClientAsyncBuilder].
+    pub fn builder() -> PkarrClientAsyncBuilder {
+        PkarrClientAsyncBuilder::default()
+    }
+
+    /// Run the client side of the obfs4-style handshake for one relay round trip, if an
+    /// [ObfuscatedTransport] key has been configured for `relay`.
+    fn begin_transport_handshake(
+        &self,
+        relay: &Url,
+    ) -> Result<Option<(Vec<u8>, ObfuscatedTransport)>> {
+        self.obfuscated_transport_keys
+            .get(relay)
+            .map(ObfuscatedTransport::client_handshake)
+            .transpose()
+    }
+
+    /// Publish a [SignedPacket] to the configured relays (if any) and the Mainline DHT, without
+    /// blocking the calling task.
+    pub async fn publish(&self, signed_packet: &SignedPacket) -> Result<()> {
+        let args = Bep44Args::try_from_packet(&signed_packet.keypair(), signed_packet.packet())?;
+
+        if let Some(relays) = &self.relays {
+            for relay in relays {
+                let url = relay.join(&signed_packet.public_key().to_string())?;
+
+                let body: reqwest::Body = match self.begin_transport_handshake(relay)? {
+                    Some((mut wire, mut transport)) => {
+                        wire.extend_from_slice(&transport.send(&args.relay_payload())?);
+                        wire.into()
+                    }
+                    // Mirrors the blocking client's `From<&Bep44Args> for reqwest::blocking::Body`.
+                    None => (&args).into(),
+                };
+
+                match self.http_client.put(url.clone()).body(body).send().await {
+                    Ok(response) if response.status().is_success() => {
+                        debug!(?url, "Published to relay");
+                    }
+                    Ok(response) => {
+                        debug!(?url, status = ?response.status(), "Relay rejected publish")
+                    }
+                    Err(error) => debug!(?url, ?error, "Failed to reach relay"),
+                }
+            }
+        }
+
+        self.dht
+            .as_async()
+            .put_mutable((&args).into())
+            .await
+            .map_err(Error::DhtPutError)?;
+
+        Ok(())
+    }
+
+    /// Verify and cache the Merkle proof headers (if any) on a relay's resolve response, as
+    /// configured via [PkarrClientAsyncBuilder::merkle_verification] for `relay`.
+    ///
+    /// A relay with no [merkle_verify_keys] entry is trusted as before: no proof is required.
+    /// One that does have an entry must carry a valid [merkle::InclusionProof] for
+    /// `(record_public_key, seq, v)`, and, once a tree head has already been cached for it, a
+    /// valid [merkle::ConsistencyProof] showing that head is a prefix of the new one — otherwise
+    /// the relay could silently roll back or fork its own log.
+    ///
+    /// Callers must *not* treat an `Err` here as fatal to the whole resolution: the relay itself
+    /// failing this check is exactly the equivocation/rollback scenario it exists to catch, so it
+    /// must only take that one relay out of consideration, not every other relay and the DHT
+    /// fallback along with it (see [Self::resolve_from_relay]).
+    fn verify_merkle_proof(
+        &self,
+        relay: &Url,
+        headers: &reqwest::header::HeaderMap,
+        record_public_key: &PublicKey,
+        seq: u64,
+        v: &[u8],
+    ) -> Result<()> {
+        let Some(relay_public_key) = self.merkle_verify_keys.get(relay) else {
+            return Ok(());
+        };
+
+        let head = headers
+            .get(merkle::TREE_HEAD_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(Error::MerkleHeadSignatureInvalid)
+            .and_then(merkle::decode_tree_head)?;
+        let proof = headers
+            .get(merkle::INCLUSION_PROOF_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(Error::MerkleInclusionProofInvalid)
+            .and_then(merkle::decode_inclusion_proof)?;
+
+        merkle::verify_inclusion(&head, &proof, relay_public_key, record_public_key, seq, v)?;
+
+        let relay_id = relay.as_str();
+        let mut cache = self.tree_head_cache.lock().expect("tree head cache lock");
+        if let Some(old_head) = cache.get(relay_id) {
+            let consistency = headers
+                .get(merkle::CONSISTENCY_PROOF_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(Error::MerkleConsistencyProofInvalid)
+                .and_then(merkle::decode_consistency_proof)?;
+
+            merkle::verify_consistency(old_head, &head, &consistency, relay_public_key)?;
+        }
+        cache.update(relay_id, head);
+
+        Ok(())
+    }
+
+    /// Try to resolve `public_key` through a single `relay`: send the request, and decode and
+    /// verify whatever comes back.
+    ///
+    /// Every fallible step after the request is sent — transport decryption, BEP44 decoding, and
+    /// [Self::verify_merkle_proof] — returns its error here rather than from [Self::resolve], so
+    /// that a relay answering with a malformed payload or a bad/missing Merkle proof (whether
+    /// from misconfiguration or an active equivocation attempt) is treated as "this relay
+    /// failed" by the caller instead of aborting resolution through every other relay and the
+    /// DHT fallback.
+    async fn resolve_from_relay(
+        &self,
+        relay: &Url,
+        public_key: &PublicKey,
+    ) -> Result<Option<SignedPacket>> {
+        let url = relay.join(&public_key.to_string())?;
+
+        let (handshake, mut transport) = match self.begin_transport_handshake(relay)? {
+            Some((handshake, transport)) => (Some(handshake), Some(transport)),
+            None => (None, None),
+        };
+
+        let mut request = self.http_client.get(url.clone());
+        if let Some(handshake) = handshake {
+            request = request.body(handshake);
+        }
+        if self.merkle_verify_keys.contains_key(relay) {
+            if let Some(head) = self
+                .tree_head_cache
+                .lock()
+                .expect("tree head cache lock")
+                .get(relay.as_str())
+            {
+                request = request.header(merkle::TREE_HEAD_SIZE_HEADER, head.size.to_string());
+            }
+        }
+
+        let Ok(response) = request.send().await else {
+            return Ok(None);
+        };
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+
+        let headers = response.headers().clone();
+        let bytes = response.bytes().await?;
+        let bytes = match &mut transport {
+            Some(transport) => Bytes::from(transport.recv(&bytes)?),
+            None => bytes,
+        };
+        let args = Bep44Args::try_from_relay_response(public_key, bytes)?;
+
+        self.verify_merkle_proof(relay, &headers, public_key, args.seq(), args.v())?;
+
+        let signed_packet = SignedPacket::try_from(args)?;
+
+        self.cache.put(public_key, &signed_packet);
+
+        Ok(Some(signed_packet))
+    }
+
+    /// Resolve the most recent [SignedPacket] for a [PublicKey].
+    ///
+    /// Tries the configured relays in order and returns the first successful response; only if
+    /// none of them answer does this fall back to querying the Mainline DHT directly.
+    pub async fn resolve(&self, public_key: &PublicKey) -> Result<Option<SignedPacket>> {
+        if let Some(cached) = self.cache.get(public_key) {
+            if cached.expires_in(self.minimum_ttl, self.maximum_ttl) > 0 {
+                return Ok(Some(cached));
+            }
+        }
+
+        if let Some(relays) = &self.relays {
+            for relay in relays {
+                match self.resolve_from_relay(relay, public_key).await {
+                    Ok(Some(signed_packet)) => return Ok(Some(signed_packet)),
+                    Ok(None) => {}
+                    Err(error) => {
+                        debug!(?relay, ?error, "Relay response rejected, trying next relay");
+                    }
+                }
+            }
+        }
+
+        let signed_packet = self
+            .dht
+            .as_async()
+            .get_mutable_most_recent(public_key)
+            .await
+            .map(SignedPacket::try_from)
+            .transpose()?;
+
+        if let Some(signed_packet) = &signed_packet {
+            self.cache.put(public_key, signed_packet);
+        }
+
+        Ok(signed_packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use reqwest::header::HeaderMap;
+    use simple_dns::{
+        rdata::{RData, A},
+        Name, ResourceRecord, CLASS,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{transport::ServerIdentity, Keypair};
+
+    fn sample_args(keypair: &Keypair) -> Bep44Args {
+        let mut packet = simple_dns::Packet::new_reply(0);
+        packet.answers.push(ResourceRecord::new(
+            Name::new("_transport_test.").unwrap(),
+            CLASS::IN,
+            30,
+            RData::A(A {
+                address: Ipv4Addr::new(1, 1, 1, 1).into(),
+            }),
+        ));
+        Bep44Args::try_from_packet(keypair, &packet).unwrap()
+    }
+
+    fn relay_url(addr: SocketAddr) -> Url {
+        format!("http://{addr}/").parse().unwrap()
+    }
+
+    /// Read a full HTTP/1.1 request (headers + body) off `stream`, returning just the body;
+    /// good enough to stand in for a relay in tests without pulling in an HTTP server crate.
+    async fn read_request_body(stream: &mut TcpStream) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        let header_end = loop {
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+
+        let content_length = String::from_utf8_lossy(&buf[..header_end])
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())
+                    .flatten()
+            })
+            .unwrap_or(0);
+
+        while buf.len() < header_end + content_length {
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+        }
+
+        buf[header_end..header_end + content_length].to_vec()
+    }
+
+    async fn write_response(stream: &mut TcpStream, status: u16, body: Vec<u8>) {
+        let status_line = format!(
+            "HTTP/1.1 {status} {}\r\nContent-Length: {}\r\n\r\n",
+            if status == 200 { "OK" } else { "Error" },
+            body.len(),
+        );
+        stream.write_all(status_line.as_bytes()).await.unwrap();
+        stream.write_all(&body).await.unwrap();
+        stream.flush().await.unwrap();
+    }
+
+    /// Accept a single connection on `listener`, reply with `(status, body)` built from the
+    /// request body it received, and hand the request body back to the caller.
+    async fn serve_one_request(
+        listener: TcpListener,
+        respond: impl FnOnce(Vec<u8>) -> (u16, Vec<u8>),
+    ) -> Vec<u8> {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let request_body = read_request_body(&mut stream).await;
+        let (status, response_body) = respond(request_body.clone());
+        write_response(&mut stream, status, response_body).await;
+        request_body
+    }
+
+    #[test]
+    fn begin_transport_handshake_is_none_for_an_unconfigured_relay() {
+        let relay: Url = "https://relay.example".parse().unwrap();
+        let client = PkarrClientAsync::builder().build().unwrap();
+
+        assert!(client.begin_transport_handshake(&relay).unwrap().is_none());
+    }
+
+    #[test]
+    fn begin_transport_handshake_returns_a_handshake_for_a_configured_relay() {
+        let relay: Url = "https://relay.example".parse().unwrap();
+        let identity = ServerIdentity::random();
+        let client = PkarrClientAsync::builder()
+            .obfuscated_transport(relay.clone(), identity.public_key())
+            .build()
+            .unwrap();
+
+        let (handshake, _transport) = client.begin_transport_handshake(&relay).unwrap().unwrap();
+
+        // Only a party who already knows `identity`'s static key can complete the handshake,
+        // same guarantee `ObfuscatedTransport` gives a real relay.
+        assert!(ObfuscatedTransport::server_handshake(&identity, &handshake).is_some());
+    }
+
+    #[tokio::test]
+    async fn resolve_falls_through_to_the_next_relay_when_the_first_errors() {
+        let keypair = Keypair::random();
+        let args = sample_args(&keypair);
+        let payload = args.relay_payload();
+
+        let failing = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let failing_addr = failing.local_addr().unwrap();
+        let failing_task = tokio::spawn(serve_one_request(failing, |_| (500, Vec::new())));
+
+        let succeeding = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let succeeding_addr = succeeding.local_addr().unwrap();
+        let succeeding_task =
+            tokio::spawn(serve_one_request(succeeding, move |_| (200, payload)));
+
+        let client = PkarrClientAsync::builder()
+            .relays(vec![relay_url(failing_addr), relay_url(succeeding_addr)])
+            .build()
+            .unwrap();
+
+        let resolved = client
+            .resolve(&keypair.public_key())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(resolved.public_key(), keypair.public_key());
+
+        failing_task.await.unwrap();
+        succeeding_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resolve_falls_through_when_a_relay_answers_200_with_a_malformed_body() {
+        let keypair = Keypair::random();
+        let args = sample_args(&keypair);
+        let payload = args.relay_payload();
+
+        // A 200 response whose body is too short to even hold a signature: decoding it as
+        // Bep44Args must fail, and that failure must not take down the other relay.
+        let malformed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let malformed_addr = malformed.local_addr().unwrap();
+        let malformed_task =
+            tokio::spawn(serve_one_request(malformed, |_| (200, vec![0u8; 4])));
+
+        let succeeding = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let succeeding_addr = succeeding.local_addr().unwrap();
+        let succeeding_task =
+            tokio::spawn(serve_one_request(succeeding, move |_| (200, payload)));
+
+        let client = PkarrClientAsync::builder()
+            .relays(vec![relay_url(malformed_addr), relay_url(succeeding_addr)])
+            .build()
+            .unwrap();
+
+        let resolved = client
+            .resolve(&keypair.public_key())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(resolved.public_key(), keypair.public_key());
+
+        malformed_task.await.unwrap();
+        succeeding_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resolve_falls_through_when_a_merkle_verified_relay_omits_its_proof() {
+        let keypair = Keypair::random();
+        let args = sample_args(&keypair);
+        let payload = args.relay_payload();
+
+        // A relay configured for merkle_verification that answers 200 without the inclusion
+        // proof headers: exactly the equivocation/rollback scenario merkle_verification exists
+        // to catch, which must take only this relay out of consideration rather than failing
+        // resolve() outright.
+        let unverified = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let unverified_addr = unverified.local_addr().unwrap();
+        let unverified_payload = payload.clone();
+        let unverified_task =
+            tokio::spawn(serve_one_request(unverified, move |_| (200, unverified_payload)));
+
+        let succeeding = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let succeeding_addr = succeeding.local_addr().unwrap();
+        let succeeding_task = tokio::spawn(serve_one_request(succeeding, move |_| (200, payload)));
+
+        let unverified_relay = relay_url(unverified_addr);
+        let relay_signing_key = Keypair::random();
+        let client = PkarrClientAsync::builder()
+            .relays(vec![unverified_relay.clone(), relay_url(succeeding_addr)])
+            .merkle_verification(unverified_relay, relay_signing_key.public_key())
+            .build()
+            .unwrap();
+
+        let resolved = client
+            .resolve(&keypair.public_key())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(resolved.public_key(), keypair.public_key());
+
+        unverified_task.await.unwrap();
+        succeeding_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn resolve_decrypts_a_response_through_an_obfuscated_relay() {
+        let keypair = Keypair::random();
+        let args = sample_args(&keypair);
+        let relay_payload = args.relay_payload();
+
+        let identity = ServerIdentity::random();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_identity = identity.clone();
+        let task = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let handshake = read_request_body(&mut stream).await;
+            let mut transport =
+                ObfuscatedTransport::server_handshake(&server_identity, &handshake).unwrap();
+            let frame = transport.send(&relay_payload).unwrap();
+            write_response(&mut stream, 200, frame).await;
+        });
+
+        let relay = relay_url(addr);
+        let client = PkarrClientAsync::builder()
+            .relays(vec![relay.clone()])
+            .obfuscated_transport(relay, identity.public_key())
+            .build()
+            .unwrap();
+
+        let resolved = client
+            .resolve(&keypair.public_key())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(resolved.public_key(), keypair.public_key());
+
+        task.await.unwrap();
+    }
+
+    fn client_with_merkle_verification(
+        relay: &Url,
+        relay_public_key: PublicKey,
+    ) -> PkarrClientAsync {
+        PkarrClientAsync::builder()
+            .merkle_verification(relay.clone(), relay_public_key)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn verify_merkle_proof_is_noop_without_a_configured_relay() {
+        let relay: Url = "https://relay.example".parse().unwrap();
+        let other_relay: Url = "https://other.example".parse().unwrap();
+        let keypair = Keypair::random();
+        let client = client_with_merkle_verification(&relay, keypair.public_key());
+
+        // `other_relay` has no merkle_verification entry, so an empty header map is accepted.
+        client
+            .verify_merkle_proof(
+                &other_relay,
+                &HeaderMap::new(),
+                &keypair.public_key(),
+                1,
+                b"v",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn verify_merkle_proof_rejects_missing_tree_head_header() {
+        let relay: Url = "https://relay.example".parse().unwrap();
+        let keypair = Keypair::random();
+        let client = client_with_merkle_verification(&relay, keypair.public_key());
+
+        assert!(client
+            .verify_merkle_proof(&relay, &HeaderMap::new(), &keypair.public_key(), 1, b"v")
+            .is_err());
+    }
+
+    #[test]
+    fn verify_merkle_proof_rejects_missing_inclusion_proof_header() {
+        let relay: Url = "https://relay.example".parse().unwrap();
+        let keypair = Keypair::random();
+        let client = client_with_merkle_verification(&relay, keypair.public_key());
+
+        let mut log = crate::merkle::MerkleLog::new();
+        log.append(&keypair.public_key(), 1, b"v");
+        let head = log.sign_head(&keypair);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            merkle::TREE_HEAD_HEADER,
+            merkle::encode_tree_head(&head).parse().unwrap(),
+        );
+
+        assert!(client
+            .verify_merkle_proof(&relay, &headers, &keypair.public_key(), 1, b"v")
+            .is_err());
+    }
+
+    #[test]
+    fn verify_merkle_proof_accepts_a_valid_proof_and_caches_the_head() {
+        let relay: Url = "https://relay.example".parse().unwrap();
+        let keypair = Keypair::random();
+        let client = client_with_merkle_verification(&relay, keypair.public_key());
+
+        let mut log = crate::merkle::MerkleLog::new();
+        log.append(&keypair.public_key(), 1, b"v");
+        let head = log.sign_head(&keypair);
+        let proof = log.inclusion_proof(0).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            merkle::TREE_HEAD_HEADER,
+            merkle::encode_tree_head(&head).parse().unwrap(),
+        );
+        headers.insert(
+            merkle::INCLUSION_PROOF_HEADER,
+            merkle::encode_inclusion_proof(&proof).parse().unwrap(),
+        );
+
+        client
+            .verify_merkle_proof(&relay, &headers, &keypair.public_key(), 1, b"v")
+            .unwrap();
+
+        assert!(client
+            .tree_head_cache
+            .lock()
+            .unwrap()
+            .get(relay.as_str())
+            .is_some());
+    }
+
+    #[test]
+    fn verify_merkle_proof_requires_consistency_proof_once_a_head_is_cached() {
+        let relay: Url = "https://relay.example".parse().unwrap();
+        let keypair = Keypair::random();
+        let client = client_with_merkle_verification(&relay, keypair.public_key());
+
+        let mut log = crate::merkle::MerkleLog::new();
+        log.append(&keypair.public_key(), 1, b"a");
+        let first_head = log.sign_head(&keypair);
+        let first_proof = log.inclusion_proof(0).unwrap();
+
+        let mut first_headers = HeaderMap::new();
+        first_headers.insert(
+            merkle::TREE_HEAD_HEADER,
+            merkle::encode_tree_head(&first_head).parse().unwrap(),
+        );
+        first_headers.insert(
+            merkle::INCLUSION_PROOF_HEADER,
+            merkle::encode_inclusion_proof(&first_proof)
+                .parse()
+                .unwrap(),
+        );
+        client
+            .verify_merkle_proof(&relay, &first_headers, &keypair.public_key(), 1, b"a")
+            .unwrap();
+
+        log.append(&keypair.public_key(), 2, b"b");
+        let second_head = log.sign_head(&keypair);
+        let second_proof = log.inclusion_proof(1).unwrap();
+
+        let mut second_headers = HeaderMap::new();
+        second_headers.insert(
+            merkle::TREE_HEAD_HEADER,
+            merkle::encode_tree_head(&second_head).parse().unwrap(),
+        );
+        second_headers.insert(
+            merkle::INCLUSION_PROOF_HEADER,
+            merkle::encode_inclusion_proof(&second_proof)
+                .parse()
+                .unwrap(),
+        );
+        // No X-Pkarr-Consistency-Proof header, even though a tree head is already cached for
+        // this relay.
+        assert!(client
+            .verify_merkle_proof(&relay, &second_headers, &keypair.public_key(), 2, b"b")
+            .is_err());
+    }
+}
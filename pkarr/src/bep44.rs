@@ -67,7 +67,18 @@ impl Bep44Args {
         })
     }
 
-    fn relay_payload(&self) -> Vec<u8> {
+    /// The sequence number carried by this record, used to verify it against a relay's Merkle
+    /// log.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// The raw `v` bytes carried by this record, used to verify it against a relay's Merkle log.
+    pub fn v(&self) -> &[u8] {
+        &self.v
+    }
+
+    pub(crate) fn relay_payload(&self) -> Vec<u8> {
         let mut body = Vec::with_capacity(64 + 8 + self.v.len());
 
         body.extend_from_slice(&self.sig.to_bytes());
@@ -85,6 +96,13 @@ impl From<&Bep44Args> for reqwest::blocking::Body {
     }
 }
 
+impl From<&Bep44Args> for reqwest::Body {
+    fn from(bep44args: &Bep44Args) -> reqwest::Body {
+        let body = bep44args.relay_payload();
+        reqwest::Body::from(body)
+    }
+}
+
 fn system_time_now() -> u64 {
     SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
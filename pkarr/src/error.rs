@@ -0,0 +1,76 @@
+//! Errors returned by this crate.
+
+use thiserror::Error;
+
+/// Errors returned by this crate.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// An ed25519 signature failed to verify.
+    #[error(transparent)]
+    Signature(#[from] ed25519_dalek::SignatureError),
+
+    /// Failed to encode/decode a DNS packet.
+    #[error(transparent)]
+    Dns(#[from] simple_dns::SimpleDnsError),
+
+    /// A relay URL failed to parse.
+    #[error(transparent)]
+    UrlParse(#[from] url::ParseError),
+
+    /// An HTTP request to a relay failed.
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+
+    /// Failed to construct or query the Mainline DHT.
+    #[error(transparent)]
+    Dht(#[from] mainline::Error),
+
+    /// Failed to put the mutable item in the DHT.
+    #[error("Failed to put the mutable item in the DHT: {0}")]
+    DhtPutError(mainline::Error),
+
+    /// A relay `put`/`get` payload's signature field was shorter than 64 bytes.
+    #[error("Relay payload invalid signature length, expected at least 64 bytes, got {0}")]
+    RelayPayloadInvalidSignatureLength(usize),
+
+    /// A relay `put`/`get` payload's sequence field was shorter than 8 bytes.
+    #[error("Relay payload invalid sequence length, expected at least 8 bytes, got {0}")]
+    RelayPayloadInvalidSequenceLength(usize),
+
+    /// [crate::merkle::MerkleLog::inclusion_proof] or
+    /// [crate::merkle::MerkleLog::consistency_proof] was asked about a leaf/tree size past the
+    /// current size of the log.
+    #[error("Merkle leaf/tree size {0} out of range of a log of size {1}")]
+    MerkleLeafOutOfRange(u64, u64),
+
+    /// A [crate::merkle::TreeHead]'s signature did not verify against the relay's public key,
+    /// or a relay's response carried no tree head header.
+    #[error("Merkle tree head signature is invalid")]
+    MerkleHeadSignatureInvalid,
+
+    /// A proof's `tree_size`/`old_size`/`new_size` did not match the tree head(s) it was
+    /// checked against, or a hex-encoded tree head or proof could not be decoded.
+    #[error("Merkle proof size does not match the tree head(s) it was checked against, or its encoding is malformed")]
+    MerkleProofSizeMismatch,
+
+    /// An [crate::merkle::InclusionProof] did not fold up to the expected tree head root, or a
+    /// relay's response carried no inclusion proof header.
+    #[error("Merkle inclusion proof is invalid")]
+    MerkleInclusionProofInvalid,
+
+    /// A [crate::merkle::ConsistencyProof] did not fold up to the expected old and new tree
+    /// head roots, or a relay's response carried no consistency proof header once one was
+    /// required.
+    #[error("Merkle consistency proof is invalid")]
+    MerkleConsistencyProofInvalid,
+
+    /// An [crate::transport::ObfuscatedTransport] frame or handshake was too short to contain
+    /// its required length prefix/counter/tag.
+    #[error("Obfuscated transport frame is too short")]
+    TransportFrameTooShort,
+
+    /// An [crate::transport::ObfuscatedTransport] frame failed AEAD authentication, meaning it
+    /// was tampered with, misdirected, or encrypted under the wrong key.
+    #[error("Obfuscated transport frame failed authentication")]
+    TransportFrameAuthenticationFailed,
+}
@@ -8,17 +8,23 @@ pub use simple_dns as dns;
 
 // Modules
 
+mod async_client;
 mod cache;
 mod client;
 mod error;
 mod keys;
+pub mod merkle;
 mod signed_packet;
+pub mod transport;
 
 // Exports
+pub use crate::async_client::PkarrClientAsync;
 pub use crate::client::PkarrClient;
 pub use crate::error::Error;
 pub use crate::keys::{Keypair, PublicKey};
+pub use crate::merkle::{InclusionProof, MerkleLog, TreeHead};
 pub use crate::signed_packet::SignedPacket;
+pub use crate::transport::{ObfuscatedTransport, ServerIdentity, Transport};
 
 /// Default minimum TTL: 30 seconds
 pub const DEFAULT_MINIMUM_TTL: u32 = 30;
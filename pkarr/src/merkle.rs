@@ -0,0 +1,752 @@
+//! A Certificate-Transparency-style (RFC 6962) append-only Merkle log, used to detect relay
+//! equivocation: a relay serving a stale (lower `seq`) [crate::SignedPacket], or different
+//! histories to different clients, cannot do so without producing an invalid proof against a
+//! tree head it previously signed.
+
+use ed25519_dalek::Signature;
+use sha2::{Digest, Sha256};
+
+use crate::{Error, Keypair, PublicKey, Result};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// HTTP response header a relay sets to the hex-encoded, signed [TreeHead] for the record it is
+/// returning, so a client resolving through it can detect equivocation.
+pub const TREE_HEAD_HEADER: &str = "X-Pkarr-Tree-Head";
+/// HTTP response header a relay sets to the hex-encoded [InclusionProof] of the record it is
+/// returning against the [TreeHead] carried in [TREE_HEAD_HEADER].
+pub const INCLUSION_PROOF_HEADER: &str = "X-Pkarr-Inclusion-Proof";
+/// HTTP response header a relay sets to the hex-encoded [ConsistencyProof] between the tree size
+/// a client last saw (sent in [TREE_HEAD_SIZE_HEADER]) and the current [TreeHead], if the relay
+/// supports it.
+pub const CONSISTENCY_PROOF_HEADER: &str = "X-Pkarr-Consistency-Proof";
+/// HTTP request header a client sets to the size of the last [TreeHead] it cached for this
+/// relay, asking for a [ConsistencyProof] against it in the response.
+pub const TREE_HEAD_SIZE_HEADER: &str = "X-Pkarr-Tree-Head-Size";
+
+/// A 32 byte SHA-256 Merkle hash.
+pub type Hash = [u8; 32];
+
+fn leaf_hash(public_key: &PublicKey, seq: u64, v: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(public_key.as_bytes());
+    hasher.update(seq.to_be_bytes());
+    hasher.update(v);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// A signed, append-only Merkle tree head: the root of the log at a given size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeHead {
+    pub size: u64,
+    pub root: Hash,
+    pub signature: [u8; 64],
+}
+
+/// Proof that a leaf at `leaf_index` is included in the tree of size `tree_size`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    pub hashes: Vec<Hash>,
+}
+
+/// Proof that the tree of size `old_size` is a prefix of the tree of size `new_size`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    pub old_size: u64,
+    pub new_size: u64,
+    pub hashes: Vec<Hash>,
+}
+
+/// An append-only Merkle log of `(public_key, seq, v)` leaves.
+///
+/// Appending a leaf only touches the "frontier": the set of subtree roots for the largest
+/// perfect subtrees that make up the current tree, so appends and tree-head recomputation are
+/// O(log n) instead of O(n).
+#[derive(Debug, Default)]
+pub struct MerkleLog {
+    /// `frontier[i]` is the root of a perfect subtree of size `2^i`, if the tree currently
+    /// contains one. Mirrors the binary representation of `size`.
+    frontier: Vec<Option<Hash>>,
+    /// All leaf hashes, kept to regenerate inclusion/consistency proofs.
+    leaves: Vec<Hash>,
+}
+
+impl MerkleLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    /// Append a newly-accepted `(public_key, seq, v)` record as a leaf, returning its index.
+    pub fn append(&mut self, public_key: &PublicKey, seq: u64, v: &[u8]) -> u64 {
+        let hash = leaf_hash(public_key, seq, v);
+        self.leaves.push(hash);
+
+        let mut carry = hash;
+        for slot in self.frontier.iter_mut() {
+            match slot.take() {
+                Some(existing) => carry = node_hash(&existing, &carry),
+                None => {
+                    *slot = Some(carry);
+                    return self.size() - 1;
+                }
+            }
+        }
+        self.frontier.push(Some(carry));
+
+        self.size() - 1
+    }
+
+    /// Root hash of the tree at its current size.
+    ///
+    /// Folds the frontier (the perfect-subtree roots [MerkleLog::append] maintains) from its
+    /// lowest occupied slot up, rather than recomputing from all the leaves, so this is O(log n)
+    /// instead of O(n).
+    pub fn root(&self) -> Hash {
+        let mut acc: Option<Hash> = None;
+        for slot in &self.frontier {
+            if let Some(hash) = slot {
+                acc = Some(match acc {
+                    Some(lower) => node_hash(hash, &lower),
+                    None => *hash,
+                });
+            }
+        }
+        acc.unwrap_or_else(|| Sha256::new().finalize().into())
+    }
+
+    /// Sign the current tree head with the relay's [Keypair].
+    pub fn sign_head(&self, keypair: &Keypair) -> TreeHead {
+        let root = self.root();
+        let signature = keypair.sign(&signable_head(self.size(), &root)).to_bytes();
+
+        TreeHead {
+            size: self.size(),
+            root,
+            signature,
+        }
+    }
+
+    /// Produce an [InclusionProof] for the leaf at `leaf_index` against the current tree.
+    pub fn inclusion_proof(&self, leaf_index: u64) -> Result<InclusionProof> {
+        if leaf_index >= self.size() {
+            return Err(Error::MerkleLeafOutOfRange(leaf_index, self.size()));
+        }
+
+        Ok(InclusionProof {
+            leaf_index,
+            tree_size: self.size(),
+            hashes: merkle_inclusion_path(&self.leaves, leaf_index),
+        })
+    }
+
+    /// Produce a [ConsistencyProof] that the tree of size `old_size` is a prefix of the current
+    /// tree.
+    pub fn consistency_proof(&self, old_size: u64) -> Result<ConsistencyProof> {
+        if old_size > self.size() {
+            return Err(Error::MerkleLeafOutOfRange(old_size, self.size()));
+        }
+
+        Ok(ConsistencyProof {
+            old_size,
+            new_size: self.size(),
+            hashes: merkle_consistency_path(&self.leaves, old_size),
+        })
+    }
+}
+
+fn signable_head(size: u64, root: &Hash) -> Vec<u8> {
+    let mut signable = Vec::with_capacity(8 + 32);
+    signable.extend_from_slice(&size.to_be_bytes());
+    signable.extend_from_slice(root);
+    signable
+}
+
+/// Recompute the root of a tree containing `leaves[..]`, per RFC 6962 §2.1.
+fn merkle_root(leaves: &[Hash]) -> Hash {
+    subtree_hash(leaves)
+}
+
+fn subtree_hash(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Sha256::new().finalize().into(),
+        1 => leaves[0],
+        n => {
+            let split = largest_power_of_two_less_than(n);
+            let left = subtree_hash(&leaves[..split]);
+            let right = subtree_hash(&leaves[split..]);
+            node_hash(&left, &right)
+        }
+    }
+}
+
+/// The inclusion path (`PATH(m, D[n])` in RFC 6962 terms) for leaf `m` in a tree of `leaves`.
+fn merkle_inclusion_path(leaves: &[Hash], m: u64) -> Vec<Hash> {
+    path(leaves, m as usize)
+}
+
+fn path(leaves: &[Hash], m: usize) -> Vec<Hash> {
+    let n = leaves.len();
+    if n <= 1 {
+        return Vec::new();
+    }
+
+    let split = largest_power_of_two_less_than(n);
+    if m < split {
+        let mut proof = path(&leaves[..split], m);
+        proof.push(subtree_hash(&leaves[split..]));
+        proof
+    } else {
+        let mut proof = path(&leaves[split..], m - split);
+        proof.push(subtree_hash(&leaves[..split]));
+        proof
+    }
+}
+
+/// The consistency path (`PROOF(m, D[n])` in RFC 6962 terms) between a tree of size `m` and the
+/// current tree of `leaves`.
+fn merkle_consistency_path(leaves: &[Hash], m: u64) -> Vec<Hash> {
+    if m == 0 || m as usize == leaves.len() {
+        return Vec::new();
+    }
+    subproof(leaves, m as usize, true)
+}
+
+fn subproof(leaves: &[Hash], m: usize, start_from_full_subtree: bool) -> Vec<Hash> {
+    let n = leaves.len();
+
+    if m == n {
+        if start_from_full_subtree {
+            Vec::new()
+        } else {
+            vec![subtree_hash(leaves)]
+        }
+    } else {
+        let split = largest_power_of_two_less_than(n);
+        if m <= split {
+            let mut proof = subproof(&leaves[..split], m, start_from_full_subtree);
+            proof.push(subtree_hash(&leaves[split..]));
+            proof
+        } else {
+            let mut proof = subproof(&leaves[split..], m - split, false);
+            proof.push(subtree_hash(&leaves[..split]));
+            proof
+        }
+    }
+}
+
+fn largest_power_of_two_less_than(n: usize) -> usize {
+    let mut k = 1;
+    while k * 2 < n {
+        k *= 2;
+    }
+    k
+}
+
+/// Verify a [TreeHead]'s signature against `relay_public_key`, the relay's signing key.
+///
+/// Every proof verification starts here: without checking the signature, a relay could pair any
+/// root it likes with a matching inclusion/consistency proof, and equivocation would go
+/// undetected.
+pub fn verify_head(head: &TreeHead, relay_public_key: &PublicKey) -> Result<()> {
+    let signature = Signature::try_from(head.signature.as_slice())
+        .map_err(|_| Error::MerkleHeadSignatureInvalid)?;
+
+    relay_public_key
+        .verify(&signable_head(head.size, &head.root), &signature)
+        .map_err(|_| Error::MerkleHeadSignatureInvalid)?;
+
+    Ok(())
+}
+
+/// Verify an [InclusionProof] for `(record_public_key, seq, v)` against a [TreeHead] signed by
+/// `relay_public_key`.
+///
+/// Implements the `verify_audit_path` algorithm from RFC 6962 §2.1.1: walk the (leaf_index,
+/// last_index) pair up the tree one level per proof entry, folding in each sibling hash on the
+/// correct side.
+pub fn verify_inclusion(
+    head: &TreeHead,
+    proof: &InclusionProof,
+    relay_public_key: &PublicKey,
+    record_public_key: &PublicKey,
+    seq: u64,
+    v: &[u8],
+) -> Result<()> {
+    verify_head(head, relay_public_key)?;
+
+    if proof.tree_size != head.size {
+        return Err(Error::MerkleProofSizeMismatch);
+    }
+
+    let mut fn_ = proof.leaf_index;
+    let mut sn = proof.tree_size - 1;
+    let mut hash = leaf_hash(record_public_key, seq, v);
+
+    for sibling in &proof.hashes {
+        if sn == 0 {
+            return Err(Error::MerkleInclusionProofInvalid);
+        }
+
+        if fn_ % 2 == 1 || fn_ == sn {
+            hash = node_hash(sibling, &hash);
+            while fn_ % 2 == 0 && fn_ != 0 {
+                fn_ /= 2;
+                sn /= 2;
+            }
+        } else {
+            hash = node_hash(&hash, sibling);
+        }
+        fn_ /= 2;
+        sn /= 2;
+    }
+
+    if sn != 0 {
+        return Err(Error::MerkleInclusionProofInvalid);
+    }
+
+    if hash == head.root {
+        Ok(())
+    } else {
+        Err(Error::MerkleInclusionProofInvalid)
+    }
+}
+
+/// Verify a [ConsistencyProof] that `old_head` is a prefix of `new_head`, both signed by
+/// `relay_public_key`.
+///
+/// Implements the `PROOF` verification algorithm from RFC 6962 §2.1.2: walk the (node, last_node)
+/// pair up the tree, tracking the old tree's root and the new tree's root in parallel so that a
+/// single pass confirms both that `old_head.root` is reproduced and that the proof folds up to
+/// `new_head.root`. A relay can't pass this unless the new log is a strict append onto the old
+/// one, so an equivocating or rolled-back history is always rejected.
+pub fn verify_consistency(
+    old_head: &TreeHead,
+    new_head: &TreeHead,
+    proof: &ConsistencyProof,
+    relay_public_key: &PublicKey,
+) -> Result<()> {
+    verify_head(old_head, relay_public_key)?;
+    verify_head(new_head, relay_public_key)?;
+
+    if proof.old_size != old_head.size || proof.new_size != new_head.size {
+        return Err(Error::MerkleProofSizeMismatch);
+    }
+
+    if old_head.size == new_head.size {
+        return if proof.hashes.is_empty() && old_head.root == new_head.root {
+            Ok(())
+        } else {
+            Err(Error::MerkleConsistencyProofInvalid)
+        };
+    }
+
+    if old_head.size == 0 {
+        return if proof.hashes.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::MerkleConsistencyProofInvalid)
+        };
+    }
+
+    if proof.hashes.is_empty() {
+        return Err(Error::MerkleConsistencyProofInvalid);
+    }
+
+    let mut node = old_head.size - 1;
+    let mut last_node = new_head.size - 1;
+    while node % 2 == 1 {
+        node /= 2;
+        last_node /= 2;
+    }
+
+    let mut hashes = proof.hashes.iter();
+    let (mut old_hash, mut new_hash) = if node == 0 {
+        (old_head.root, old_head.root)
+    } else {
+        let first = *hashes.next().ok_or(Error::MerkleConsistencyProofInvalid)?;
+        (first, first)
+    };
+
+    for sibling in hashes {
+        if last_node == 0 {
+            return Err(Error::MerkleConsistencyProofInvalid);
+        }
+
+        if node % 2 == 1 || node == last_node {
+            old_hash = node_hash(sibling, &old_hash);
+            new_hash = node_hash(sibling, &new_hash);
+            while node % 2 == 0 && node != 0 {
+                node /= 2;
+                last_node /= 2;
+            }
+        } else {
+            new_hash = node_hash(&new_hash, sibling);
+        }
+        node /= 2;
+        last_node /= 2;
+    }
+
+    if old_hash != old_head.root || last_node != 0 || new_hash != new_head.root {
+        return Err(Error::MerkleConsistencyProofInvalid);
+    }
+
+    Ok(())
+}
+
+/// A client-side cache of the last tree head seen from each relay, used to require a
+/// [ConsistencyProof] on every subsequent fetch so the relay can never rewrite or shrink its
+/// history without detection.
+///
+/// Wired into [crate::PkarrClientAsync] via
+/// [crate::async_client::PkarrClientAsyncBuilder::merkle_verification]; the blocking
+/// [crate::client::PkarrClient] doesn't yet configure one, so equivocation/rollback detection is
+/// only available to callers that switch to the async client.
+#[derive(Debug, Default)]
+pub struct TreeHeadCache {
+    last_seen: std::collections::HashMap<String, TreeHead>,
+}
+
+impl TreeHeadCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The last tree head seen for `relay`, if any.
+    pub fn get(&self, relay: &str) -> Option<&TreeHead> {
+        self.last_seen.get(relay)
+    }
+
+    /// Accept a new tree head from `relay`, after its [ConsistencyProof] against the previously
+    /// cached head (if any) has been verified by the caller.
+    pub fn update(&mut self, relay: &str, head: TreeHead) {
+        self.last_seen.insert(relay.to_string(), head);
+    }
+}
+
+/// Encode a [TreeHead] as `size || root || signature`, hex-encoded for use in an HTTP header.
+pub fn encode_tree_head(head: &TreeHead) -> String {
+    let mut bytes = Vec::with_capacity(8 + 32 + 64);
+    bytes.extend_from_slice(&head.size.to_be_bytes());
+    bytes.extend_from_slice(&head.root);
+    bytes.extend_from_slice(&head.signature);
+    to_hex(&bytes)
+}
+
+/// Inverse of [encode_tree_head].
+pub fn decode_tree_head(encoded: &str) -> Result<TreeHead> {
+    let bytes = from_hex(encoded).ok_or(Error::MerkleProofSizeMismatch)?;
+    if bytes.len() != 8 + 32 + 64 {
+        return Err(Error::MerkleProofSizeMismatch);
+    }
+
+    let size = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+    let root: Hash = bytes[8..40].try_into().unwrap();
+    let signature: [u8; 64] = bytes[40..].try_into().unwrap();
+
+    Ok(TreeHead {
+        size,
+        root,
+        signature,
+    })
+}
+
+/// Encode an [InclusionProof] as `leaf_index || tree_size || hashes`, hex-encoded for use in an
+/// HTTP header.
+pub fn encode_inclusion_proof(proof: &InclusionProof) -> String {
+    let mut bytes = Vec::with_capacity(16 + proof.hashes.len() * 32);
+    bytes.extend_from_slice(&proof.leaf_index.to_be_bytes());
+    bytes.extend_from_slice(&proof.tree_size.to_be_bytes());
+    for hash in &proof.hashes {
+        bytes.extend_from_slice(hash);
+    }
+    to_hex(&bytes)
+}
+
+/// Inverse of [encode_inclusion_proof].
+pub fn decode_inclusion_proof(encoded: &str) -> Result<InclusionProof> {
+    let bytes = from_hex(encoded).ok_or(Error::MerkleProofSizeMismatch)?;
+    if bytes.len() < 16 || (bytes.len() - 16) % 32 != 0 {
+        return Err(Error::MerkleProofSizeMismatch);
+    }
+
+    let leaf_index = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+    let tree_size = u64::from_be_bytes(bytes[8..16].try_into().unwrap());
+    let hashes = bytes[16..]
+        .chunks_exact(32)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect();
+
+    Ok(InclusionProof {
+        leaf_index,
+        tree_size,
+        hashes,
+    })
+}
+
+/// Encode a [ConsistencyProof] as `old_size || new_size || hashes`, hex-encoded for use in an
+/// HTTP header.
+pub fn encode_consistency_proof(proof: &ConsistencyProof) -> String {
+    let mut bytes = Vec::with_capacity(16 + proof.hashes.len() * 32);
+    bytes.extend_from_slice(&proof.old_size.to_be_bytes());
+    bytes.extend_from_slice(&proof.new_size.to_be_bytes());
+    for hash in &proof.hashes {
+        bytes.extend_from_slice(hash);
+    }
+    to_hex(&bytes)
+}
+
+/// Inverse of [encode_consistency_proof].
+pub fn decode_consistency_proof(encoded: &str) -> Result<ConsistencyProof> {
+    let bytes = from_hex(encoded).ok_or(Error::MerkleProofSizeMismatch)?;
+    if bytes.len() < 16 || (bytes.len() - 16) % 32 != 0 {
+        return Err(Error::MerkleProofSizeMismatch);
+    }
+
+    let old_size = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+    let new_size = u64::from_be_bytes(bytes[8..16].try_into().unwrap());
+    let hashes = bytes[16..]
+        .chunks_exact(32)
+        .map(|chunk| chunk.try_into().unwrap())
+        .collect();
+
+    Ok(ConsistencyProof {
+        old_size,
+        new_size,
+        hashes,
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_and_root_is_deterministic() {
+        let keypair = Keypair::random();
+        let mut log = MerkleLog::new();
+
+        log.append(&keypair.public_key(), 1, b"a");
+        log.append(&keypair.public_key(), 2, b"b");
+        log.append(&keypair.public_key(), 3, b"c");
+
+        let expected = merkle_root(&log.leaves);
+
+        assert_eq!(log.root(), expected);
+        assert_eq!(log.size(), 3);
+    }
+
+    #[test]
+    fn inclusion_proof_round_trips_for_single_leaf_tree() {
+        let keypair = Keypair::random();
+        let mut log = MerkleLog::new();
+        log.append(&keypair.public_key(), 1, b"only");
+
+        let head = log.sign_head(&keypair);
+        let proof = log.inclusion_proof(0).unwrap();
+
+        assert_eq!(proof.hashes.len(), 0);
+        assert_eq!(head.root, leaf_hash(&keypair.public_key(), 1, b"only"));
+    }
+
+    #[test]
+    fn inclusion_proof_out_of_range_errors() {
+        let log = MerkleLog::new();
+        assert!(log.inclusion_proof(0).is_err());
+    }
+
+    #[test]
+    fn consistency_proof_against_same_size_is_empty() {
+        let keypair = Keypair::random();
+        let mut log = MerkleLog::new();
+        log.append(&keypair.public_key(), 1, b"a");
+        log.append(&keypair.public_key(), 2, b"b");
+
+        let proof = log.consistency_proof(log.size()).unwrap();
+        assert!(proof.hashes.is_empty());
+    }
+
+    #[test]
+    fn inclusion_proof_verifies_every_leaf_of_a_four_leaf_tree() {
+        let keypair = Keypair::random();
+        let mut log = MerkleLog::new();
+
+        let records = [
+            (1u64, b"aaaa".as_slice()),
+            (2, b"bbbb"),
+            (3, b"cccc"),
+            (4, b"dddd"),
+        ];
+        for (seq, v) in records {
+            log.append(&keypair.public_key(), seq, v);
+        }
+
+        let head = log.sign_head(&keypair);
+
+        for (index, (seq, v)) in records.into_iter().enumerate() {
+            let proof = log.inclusion_proof(index as u64).unwrap();
+            verify_inclusion(
+                &head,
+                &proof,
+                &keypair.public_key(),
+                &keypair.public_key(),
+                seq,
+                v,
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn inclusion_proof_rejects_tampered_value() {
+        let keypair = Keypair::random();
+        let mut log = MerkleLog::new();
+        log.append(&keypair.public_key(), 1, b"a");
+        log.append(&keypair.public_key(), 2, b"b");
+        log.append(&keypair.public_key(), 3, b"c");
+
+        let head = log.sign_head(&keypair);
+        let proof = log.inclusion_proof(1).unwrap();
+
+        assert!(verify_inclusion(
+            &head,
+            &proof,
+            &keypair.public_key(),
+            &keypair.public_key(),
+            2,
+            b"tampered"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn inclusion_proof_rejects_head_signed_by_a_different_relay() {
+        let relay = Keypair::random();
+        let other_relay = Keypair::random();
+        let mut log = MerkleLog::new();
+        log.append(&relay.public_key(), 1, b"a");
+
+        let mut head = log.sign_head(&relay);
+        head.signature = other_relay
+            .sign(&signable_head(head.size, &head.root))
+            .to_bytes();
+        let proof = log.inclusion_proof(0).unwrap();
+
+        assert!(verify_inclusion(
+            &head,
+            &proof,
+            &relay.public_key(),
+            &relay.public_key(),
+            1,
+            b"a"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn consistency_proof_verifies_across_growth() {
+        let relay = Keypair::random();
+        let keypair = Keypair::random();
+        let mut log = MerkleLog::new();
+
+        log.append(&keypair.public_key(), 1, b"a");
+        log.append(&keypair.public_key(), 2, b"b");
+        let old_head = log.sign_head(&relay);
+
+        log.append(&keypair.public_key(), 3, b"c");
+        log.append(&keypair.public_key(), 4, b"d");
+        let new_head = log.sign_head(&relay);
+
+        let proof = log.consistency_proof(old_head.size).unwrap();
+
+        verify_consistency(&old_head, &new_head, &proof, &relay.public_key()).unwrap();
+    }
+
+    #[test]
+    fn consistency_proof_rejects_rollback() {
+        let relay = Keypair::random();
+        let keypair = Keypair::random();
+        let mut log = MerkleLog::new();
+
+        log.append(&keypair.public_key(), 1, b"a");
+        log.append(&keypair.public_key(), 2, b"b");
+        let old_head = log.sign_head(&relay);
+
+        log.append(&keypair.public_key(), 3, b"c");
+        let new_head = log.sign_head(&relay);
+
+        let proof = log.consistency_proof(old_head.size).unwrap();
+
+        // A relay that rewrites the tail of its own log produces a different root at the same
+        // size; the proof generated against the *live* (rewritten) log must not verify against
+        // the tree head the client had already cached for that size.
+        let mut rewritten_log = MerkleLog::new();
+        rewritten_log.append(&keypair.public_key(), 1, b"a");
+        rewritten_log.append(&keypair.public_key(), 2, b"b");
+        rewritten_log.append(&keypair.public_key(), 99, b"rewritten");
+        let rewritten_proof = rewritten_log.consistency_proof(old_head.size).unwrap();
+
+        assert!(
+            verify_consistency(&old_head, &new_head, &rewritten_proof, &relay.public_key())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn tree_head_and_proofs_round_trip_through_hex_encoding() {
+        let relay = Keypair::random();
+        let keypair = Keypair::random();
+        let mut log = MerkleLog::new();
+        log.append(&keypair.public_key(), 1, b"a");
+        log.append(&keypair.public_key(), 2, b"b");
+        let old_head = log.sign_head(&relay);
+
+        log.append(&keypair.public_key(), 3, b"c");
+        let new_head = log.sign_head(&relay);
+
+        let inclusion = log.inclusion_proof(0).unwrap();
+        let consistency = log.consistency_proof(old_head.size).unwrap();
+
+        assert_eq!(
+            decode_tree_head(&encode_tree_head(&new_head)).unwrap(),
+            new_head
+        );
+        assert_eq!(
+            decode_inclusion_proof(&encode_inclusion_proof(&inclusion)).unwrap(),
+            inclusion
+        );
+        assert_eq!(
+            decode_consistency_proof(&encode_consistency_proof(&consistency)).unwrap(),
+            consistency
+        );
+    }
+}
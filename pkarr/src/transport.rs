@@ -0,0 +1,345 @@
+//! An optional obfs4/o5-style obfuscated [Transport] for relay traffic.
+//!
+//! Plain HTTP relay traffic is trivially fingerprinted by a censor doing deep packet inspection.
+//! [ObfuscatedTransport] wraps a connection in an HMAC-authenticated ephemeral Diffie-Hellman
+//! handshake, followed by a ChaCha20-Poly1305 AEAD encrypted channel with no static plaintext
+//! markers, so a prober without the server's static key cannot complete a handshake or
+//! distinguish the channel from noise, and an active attacker who modifies a frame in transit is
+//! caught by the authentication tag rather than silently flipping plaintext bits.
+//!
+//! BEP44 DHT UDP traffic is just as fingerprintable, but is not covered: the raw KRPC wire
+//! format is read and written by [mainline]'s own socket layer, which is outside this crate, so
+//! there's nowhere in `pkarr` to splice an obfuscated channel in without forking that crate. A
+//! censor can still trivially flag the Mainline DHT side of a resolution.
+//!
+//! The handshake sends the client's ephemeral X25519 public key as plain key bytes rather than
+//! Elligator2-encoded. True obfs4 encodes the key as an Elligator2 representative so the bytes
+//! are uniformly distributed; that needs access to the underlying secret scalar, which
+//! `x25519_dalek::EphemeralSecret` deliberately keeps private, and this crate's `x25519-dalek`
+//! dependency doesn't expose an Elligator2 encoding of it either. Plain X25519 public key bytes
+//! are *not* uniform — the top bit of the little-endian u-coordinate is biased toward zero,
+//! since valid coordinates are reduced mod a prime just under 2^255 — so a sophisticated censor
+//! checking for that bias can still flag this handshake; it stops casual fingerprinting and
+//! protocol-marker matching, not a DPI box built specifically to detect X25519 key exchanges.
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Nonce,
+};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey as X25519PublicKey};
+
+use crate::{Error, Result};
+
+const PADDING_MAX: usize = 128;
+const MAC_LEN: usize = 32;
+
+/// A transport that a client can use to protect the payloads it exchanges with a relay, and that
+/// a relay can terminate. The frames travel inside ordinary HTTP request and response bodies;
+/// the HTTP method, URL and headers are not obfuscated.
+///
+/// Only [crate::PkarrClientAsync] wires this in today, via
+/// [crate::async_client::PkarrClientAsyncBuilder::obfuscated_transport]; the blocking
+/// [crate::client::PkarrClient] doesn't yet expose a builder method for it.
+///
+/// Implementations are responsible for framing: `send`/`recv` exchange whole application-layer
+/// messages (e.g. a [crate::bep44::Bep44Args] relay payload). Messages are length-prefixed and
+/// padded to a bucketed size before encryption, so the ciphertext length leaks only the bucket a
+/// message falls into rather than its exact size, and every frame carries its own nonce so
+/// out-of-order or lost datagrams never cause keystream reuse.
+pub trait Transport {
+    /// Encrypt and frame `message` for the wire.
+    fn send(&mut self, message: &[u8]) -> Result<Vec<u8>>;
+
+    /// Decrypt a frame received from the wire back into the original message.
+    fn recv(&mut self, frame: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// The server's long-term identity for the obfuscated handshake, analogous to an obfs4 bridge
+/// line's public key: clients must already know it, since it is what lets the server's MAC
+/// silently reject active probers who don't.
+#[derive(Clone)]
+pub struct ServerIdentity {
+    secret: StaticSecretBytes,
+}
+
+/// Wraps the server's static X25519 secret. Neither this nor [ServerIdentity] implements
+/// `Debug`, so the key material is never accidentally logged.
+#[derive(Clone)]
+struct StaticSecretBytes([u8; 32]);
+
+impl ServerIdentity {
+    /// Generate a new random server identity.
+    pub fn random() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self {
+            secret: StaticSecretBytes(bytes),
+        }
+    }
+
+    fn static_secret(&self) -> x25519_dalek::StaticSecret {
+        x25519_dalek::StaticSecret::from(self.secret.0)
+    }
+
+    /// The server's static public key, distributed out of band to clients (as part of the
+    /// relay's address, like an obfs4 bridge line).
+    pub fn public_key(&self) -> X25519PublicKey {
+        X25519PublicKey::from(&self.static_secret())
+    }
+}
+
+/// An obfs4/o5-style obfuscated transport: an ephemeral Diffie-Hellman handshake MAC'd with the
+/// server's static key, followed by a ChaCha20-Poly1305 AEAD encrypted channel keyed by the
+/// shared secret.
+///
+/// The client-to-server and server-to-client directions use independent keys (ntor-style), and
+/// every frame carries an explicit counter used as its nonce, so frames may be reordered or
+/// dropped (as can happen on a datagram transport) without ever reusing a keystream for two
+/// different messages.
+pub struct ObfuscatedTransport {
+    send_key: [u8; 32],
+    recv_key: [u8; 32],
+    send_counter: u64,
+}
+
+impl ObfuscatedTransport {
+    /// Run the client side of the handshake against `server_public_key`, returning the bytes to
+    /// send on the wire and the ready-to-use transport.
+    ///
+    /// The returned handshake bytes are the client's ephemeral X25519 public key, padded with
+    /// random bytes to a random length, followed by an HMAC-SHA256 tag keyed by
+    /// `server_public_key` so that a prober without that key cannot construct a handshake the
+    /// server will accept.
+    pub fn client_handshake(server_public_key: &X25519PublicKey) -> Result<(Vec<u8>, Self)> {
+        let secret = EphemeralSecret::random_from_rng(rand::thread_rng());
+        let public_key = X25519PublicKey::from(&secret);
+
+        let shared_secret = secret.diffie_hellman(server_public_key);
+
+        let mut rng = rand::thread_rng();
+        let padding_len = (rng.next_u32() as usize) % PADDING_MAX;
+        let mut padding = vec![0u8; padding_len];
+        rng.fill_bytes(&mut padding);
+
+        let mut handshake = Vec::with_capacity(32 + padding_len + MAC_LEN);
+        handshake.extend_from_slice(public_key.as_bytes());
+        handshake.extend_from_slice(&padding);
+        handshake.extend_from_slice(&mac(server_public_key.as_bytes(), &handshake));
+
+        let (send_key, recv_key) = derive_directional_keys(shared_secret.as_bytes());
+
+        Ok((
+            handshake,
+            Self {
+                send_key,
+                recv_key,
+                send_counter: 0,
+            },
+        ))
+    }
+
+    /// Run the server side of the handshake given a client's handshake bytes and this server's
+    /// identity. Returns `None` (rather than an error) if the MAC doesn't verify, so that an
+    /// active prober gets no distinguishable response from "nothing is listening here".
+    pub fn server_handshake(identity: &ServerIdentity, handshake: &[u8]) -> Option<Self> {
+        if handshake.len() < 32 + MAC_LEN {
+            return None;
+        }
+
+        let (body, tag) = handshake.split_at(handshake.len() - MAC_LEN);
+        let expected = mac(identity.public_key().as_bytes(), body);
+        if !constant_time_eq(&expected, tag) {
+            return None;
+        }
+
+        let client_public_bytes: [u8; 32] = body[..32].try_into().ok()?;
+        let client_public = X25519PublicKey::from(client_public_bytes);
+
+        let shared_secret = identity.static_secret().diffie_hellman(&client_public);
+        // The server's view is the mirror image of the client's: what the client sends with,
+        // the server receives with, and vice versa.
+        let (client_to_server, server_to_client) =
+            derive_directional_keys(shared_secret.as_bytes());
+
+        Some(Self {
+            send_key: server_to_client,
+            recv_key: client_to_server,
+            send_counter: 0,
+        })
+    }
+}
+
+impl Transport for ObfuscatedTransport {
+    fn send(&mut self, message: &[u8]) -> Result<Vec<u8>> {
+        let counter = self.send_counter;
+        self.send_counter += 1;
+
+        let padded = pad_message(message);
+        let ciphertext = frame_cipher(&self.send_key)
+            .encrypt(&frame_nonce(counter), Payload::from(padded.as_slice()))
+            .map_err(|_| Error::TransportFrameAuthenticationFailed)?;
+
+        let mut frame = Vec::with_capacity(8 + ciphertext.len());
+        frame.extend_from_slice(&counter.to_be_bytes());
+        frame.extend_from_slice(&ciphertext);
+        Ok(frame)
+    }
+
+    fn recv(&mut self, frame: &[u8]) -> Result<Vec<u8>> {
+        if frame.len() < 8 {
+            return Err(Error::TransportFrameTooShort);
+        }
+        let (counter_bytes, ciphertext) = frame.split_at(8);
+        let counter = u64::from_be_bytes(counter_bytes.try_into().unwrap());
+
+        let padded = frame_cipher(&self.recv_key)
+            .decrypt(&frame_nonce(counter), Payload::from(ciphertext))
+            .map_err(|_| Error::TransportFrameAuthenticationFailed)?;
+
+        unpad_message(&padded)
+    }
+}
+
+/// Derive independent client-to-server and server-to-client keys (ntor-style) from the shared
+/// secret, rather than using a single key for both directions of the channel.
+fn derive_directional_keys(shared_secret: &[u8; 32]) -> ([u8; 32], [u8; 32]) {
+    let client_to_server = mac(b"pkarr-obfuscated-transport-v1-c2s", shared_secret);
+    let server_to_client = mac(b"pkarr-obfuscated-transport-v1-s2c", shared_secret);
+    (client_to_server, server_to_client)
+}
+
+/// A ChaCha20-Poly1305 AEAD cipher keyed by one direction's key. Every frame is nonced by its
+/// own counter (see [frame_nonce]), so no two frames in the same direction ever share a
+/// keystream, and the Poly1305 tag rejects any frame an active attacker has tampered with.
+fn frame_cipher(key: &[u8; 32]) -> ChaCha20Poly1305 {
+    ChaCha20Poly1305::new(key.into())
+}
+
+/// The per-frame nonce: the frame counter left-padded with zeroes, so frames may be reordered or
+/// dropped without ever reusing a nonce for two different messages in the same direction.
+fn frame_nonce(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce.into()
+}
+
+/// Length-prefix `message` and pad it up to the next [PADDING_MAX]-sized bucket (plus one
+/// randomly-sized bucket of jitter) so the ciphertext length reveals only a size bucket, not the
+/// exact message length.
+fn pad_message(message: &[u8]) -> Vec<u8> {
+    let prefixed_len = 4 + message.len();
+    let bucket = ((prefixed_len / PADDING_MAX) + 1) * PADDING_MAX;
+    let jitter = (rand::thread_rng().next_u32() as usize) % PADDING_MAX;
+    let target_len = bucket + jitter;
+
+    let mut padded = Vec::with_capacity(target_len);
+    padded.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    padded.extend_from_slice(message);
+
+    let mut filler = vec![0u8; target_len - prefixed_len];
+    rand::thread_rng().fill_bytes(&mut filler);
+    padded.extend_from_slice(&filler);
+
+    padded
+}
+
+/// Reverse of [pad_message]: read the length prefix and discard the padding.
+fn unpad_message(padded: &[u8]) -> Result<Vec<u8>> {
+    if padded.len() < 4 {
+        return Err(Error::TransportFrameTooShort);
+    }
+    let (len_bytes, rest) = padded.split_at(4);
+    let len = u32::from_be_bytes(len_bytes.try_into().unwrap()) as usize;
+
+    rest.get(..len)
+        .map(|message| message.to_vec())
+        .ok_or(Error::TransportFrameTooShort)
+}
+
+fn mac(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_derives_matching_ciphers() {
+        let identity = ServerIdentity::random();
+
+        let (handshake, mut client) =
+            ObfuscatedTransport::client_handshake(&identity.public_key()).unwrap();
+        let mut server = ObfuscatedTransport::server_handshake(&identity, &handshake).unwrap();
+
+        let frame = client.send(b"hello relay").unwrap();
+        let message = server.recv(&frame).unwrap();
+
+        assert_eq!(message, b"hello relay");
+    }
+
+    #[test]
+    fn server_rejects_handshake_with_wrong_identity() {
+        let identity = ServerIdentity::random();
+        let other = ServerIdentity::random();
+
+        let (handshake, _client) =
+            ObfuscatedTransport::client_handshake(&identity.public_key()).unwrap();
+
+        assert!(ObfuscatedTransport::server_handshake(&other, &handshake).is_none());
+    }
+
+    #[test]
+    fn frames_survive_reordering() {
+        let identity = ServerIdentity::random();
+
+        let (handshake, mut client) =
+            ObfuscatedTransport::client_handshake(&identity.public_key()).unwrap();
+        let mut server = ObfuscatedTransport::server_handshake(&identity, &handshake).unwrap();
+
+        let first = client.send(b"first").unwrap();
+        let second = client.send(b"second").unwrap();
+
+        // The server decrypts whichever frame arrives first, out of order, since each carries
+        // its own counter rather than relying on a shared running keystream.
+        assert_eq!(server.recv(&second).unwrap(), b"second");
+        assert_eq!(server.recv(&first).unwrap(), b"first");
+    }
+
+    #[test]
+    fn repeated_identical_messages_produce_different_ciphertext() {
+        let identity = ServerIdentity::random();
+        let (handshake, mut client) =
+            ObfuscatedTransport::client_handshake(&identity.public_key()).unwrap();
+        let _server = ObfuscatedTransport::server_handshake(&identity, &handshake).unwrap();
+
+        let first = client.send(b"same message").unwrap();
+        let second = client.send(b"same message").unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn tampered_frame_fails_authentication() {
+        let identity = ServerIdentity::random();
+
+        let (handshake, mut client) =
+            ObfuscatedTransport::client_handshake(&identity.public_key()).unwrap();
+        let mut server = ObfuscatedTransport::server_handshake(&identity, &handshake).unwrap();
+
+        let mut frame = client.send(b"hello relay").unwrap();
+        let last = frame.len() - 1;
+        frame[last] ^= 1;
+
+        assert!(server.recv(&frame).is_err());
+    }
+}
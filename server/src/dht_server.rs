@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fmt::Debug,
     net::{SocketAddr, ToSocketAddrs},
 };
@@ -8,20 +9,26 @@ use pkarr::{
         self,
         rpc::{
             messages::{
-                GetMutableResponseArguments, GetValueRequestArguments, RequestSpecific,
-                RequestTypeSpecific, ResponseSpecific,
+                GetMutableResponseArguments, GetValueRequestArguments, PutMutableRequestArguments,
+                PutRequestSpecific, RequestSpecific, RequestTypeSpecific, ResponseSpecific,
             },
             Rpc,
         },
         server::Server,
         MutableItem,
     },
-    PkarrCache,
+    merkle::{self, InclusionProof, MerkleLog, TreeHead},
+    transport::{ObfuscatedTransport, ServerIdentity, Transport},
+    Keypair, PkarrCache,
 };
 
 use tracing::debug;
 
-use crate::{cache::HeedPkarrCache, rate_limiting::IpRateLimiter};
+use crate::{
+    cache::HeedPkarrCache,
+    module::{run_on_body, run_on_get, run_on_put, ModuleDecision, RelayModule},
+    rate_limiting::IpRateLimiter,
+};
 
 /// DhtServer with Rate limiting
 pub struct DhtServer {
@@ -31,6 +38,14 @@ pub struct DhtServer {
     minimum_ttl: u32,
     maximum_ttl: u32,
     rate_limiter: IpRateLimiter,
+    modules: Vec<Box<dyn RelayModule>>,
+    transport_identity: Option<ServerIdentity>,
+    /// The tamper-evident log and its signing key, present only when
+    /// [DhtServer::with_merkle_log] was configured.
+    merkle: Option<(MerkleLog, Keypair)>,
+    /// The most recently appended leaf index for each record's public key, so an
+    /// [InclusionProof] can be produced for the latest accepted value of a key.
+    merkle_leaf_indices: HashMap<Vec<u8>, u64>,
 }
 
 impl Debug for DhtServer {
@@ -61,8 +76,121 @@ impl DhtServer {
             minimum_ttl,
             maximum_ttl,
             rate_limiter,
+            modules: Vec::new(),
+            transport_identity: None,
+            merkle: None,
+            merkle_leaf_indices: HashMap::new(),
         }
     }
+
+    /// Register a [RelayModule], giving it a chance to accept or reject requests before the
+    /// default cache/hydration/rate-limiting behavior runs. Modules run in registration order
+    /// and the first rejection wins.
+    pub fn with_module(mut self, module: Box<dyn RelayModule>) -> Self {
+        self.modules.push(module);
+        self
+    }
+
+    /// Terminate the obfs4-style [ObfuscatedTransport] for this server's relay HTTP channel,
+    /// using `identity` as its long-term key.
+    ///
+    /// This only covers the out-of-band relay HTTP requests that carry a
+    /// [pkarr::bep44::Bep44Args] payload to this server; `handle_request` below still only ever
+    /// sees already-decoded DHT [RequestSpecific] messages, since the raw BEP44 KRPC wire format
+    /// is owned by [mainline]'s own socket layer and outside this crate's control.
+    pub fn with_obfuscated_transport(mut self, identity: ServerIdentity) -> Self {
+        self.transport_identity = Some(identity);
+        self
+    }
+
+    /// The identity configured via [DhtServer::with_obfuscated_transport], if any.
+    pub fn transport_identity(&self) -> Option<&ServerIdentity> {
+        self.transport_identity.as_ref()
+    }
+
+    /// Run the server side of the obfs4-style handshake and decrypt one frame of an obfuscated
+    /// relay HTTP request body, given the raw `handshake` and `frame` bytes the relay's HTTP
+    /// handler read off the request.
+    ///
+    /// Returns the request's decrypted bytes together with the now-established
+    /// [ObfuscatedTransport] session, so the caller can reuse it to encrypt the reply with
+    /// [Transport::send] instead of starting a fresh handshake neither side asked for. Returns
+    /// `None` if [DhtServer::with_obfuscated_transport] wasn't configured, the handshake MAC
+    /// doesn't verify, or the frame fails AEAD authentication — in every case the relay's HTTP
+    /// handler should respond exactly as it would to an unroutable request, so an active prober
+    /// gets no distinguishable signal.
+    pub fn decode_obfuscated_request(
+        &self,
+        handshake: &[u8],
+        frame: &[u8],
+    ) -> Option<(ObfuscatedTransport, Vec<u8>)> {
+        let identity = self.transport_identity.as_ref()?;
+        let mut transport = ObfuscatedTransport::server_handshake(identity, handshake)?;
+        let message = transport.recv(frame).ok()?;
+        Some((transport, message))
+    }
+
+    /// Enable the tamper-evident [pkarr::merkle::MerkleLog]: every accepted put becomes a leaf,
+    /// and the log's tree head is signed with `keypair` so clients can detect a relay serving a
+    /// stale record or a different history to different clients.
+    pub fn with_merkle_log(mut self, keypair: Keypair) -> Self {
+        self.merkle = Some((MerkleLog::new(), keypair));
+        self
+    }
+
+    /// The current signed [TreeHead] of this server's Merkle log, if
+    /// [DhtServer::with_merkle_log] was configured.
+    pub fn tree_head(&self) -> Option<TreeHead> {
+        self.merkle
+            .as_ref()
+            .map(|(log, keypair)| log.sign_head(keypair))
+    }
+
+    /// An [InclusionProof] for the most recently accepted record under `public_key`, against the
+    /// current [DhtServer::tree_head].
+    ///
+    /// Returns `None` if the Merkle log isn't enabled or `public_key` has never been put.
+    pub fn inclusion_proof(&self, public_key: &pkarr::PublicKey) -> Option<InclusionProof> {
+        let (log, _) = self.merkle.as_ref()?;
+        let leaf_index = *self
+            .merkle_leaf_indices
+            .get(public_key.as_bytes().as_ref())?;
+        log.inclusion_proof(leaf_index).ok()
+    }
+
+    /// The hex-encoded `(`[merkle::TREE_HEAD_HEADER]`, `[merkle::INCLUSION_PROOF_HEADER]`)`
+    /// header pair a relay's HTTP resolve endpoint should set on its response for `public_key`,
+    /// so a client can detect the relay serving a stale record or a different history to
+    /// different clients. Returns `None` on the same conditions as [DhtServer::inclusion_proof].
+    pub fn proof_headers(
+        &self,
+        public_key: &pkarr::PublicKey,
+    ) -> Option<[(&'static str, String); 2]> {
+        let head = self.tree_head()?;
+        let proof = self.inclusion_proof(public_key)?;
+
+        Some([
+            (merkle::TREE_HEAD_HEADER, merkle::encode_tree_head(&head)),
+            (
+                merkle::INCLUSION_PROOF_HEADER,
+                merkle::encode_inclusion_proof(&proof),
+            ),
+        ])
+    }
+
+    /// The hex-encoded `(`[merkle::CONSISTENCY_PROOF_HEADER]`)` header a relay's HTTP resolve
+    /// endpoint should set on its response when the client's request carried
+    /// [merkle::TREE_HEAD_SIZE_HEADER], proving the client's previously cached tree head of size
+    /// `old_size` is a prefix of the current log.
+    pub fn consistency_proof_header(&self, old_size: u64) -> Option<(&'static str, String)> {
+        let (log, _) = self.merkle.as_ref()?;
+        let proof = log.consistency_proof(old_size).ok()?;
+
+        Some((
+            merkle::CONSISTENCY_PROOF_HEADER,
+            merkle::encode_consistency_proof(&proof),
+        ))
+    }
 }
 
 impl Server for DhtServer {
@@ -78,6 +206,11 @@ impl Server for DhtServer {
             ..
         } = request
         {
+            if let ModuleDecision::Reject(reason) = run_on_get(&self.modules, target) {
+                debug!(?target, reason, "Module rejected get request");
+                return;
+            }
+
             let should_query = if let Some(cached) = self.cache.get(target) {
                 debug!(
                     public_key = ?cached.public_key(),
@@ -148,6 +281,50 @@ impl Server for DhtServer {
             }
         };
 
+        if let RequestSpecific {
+            request_type:
+                RequestTypeSpecific::PutValue(PutRequestSpecific::Mutable(PutMutableRequestArguments {
+                    k,
+                    seq,
+                    v,
+                    ..
+                })),
+            ..
+        } = request
+        {
+            if let ModuleDecision::Reject(reason) = run_on_body(&self.modules, v) {
+                debug!(?from, reason, "Module rejected put value");
+                return;
+            }
+
+            if let Ok(public_key) = pkarr::PublicKey::try_from(k.as_slice()) {
+                if let ModuleDecision::Reject(reason) =
+                    run_on_put(&self.modules, &public_key, *seq, v)
+                {
+                    debug!(?from, ?public_key, reason, "Module rejected put request");
+                    return;
+                }
+
+                if let Some((log, _)) = self.merkle.as_mut() {
+                    // BEP44 `seq` is signed; a negative value is never a value we'd cache or
+                    // republish (see the `seq: i64` plumbing in `RelayModule::on_put`), so it
+                    // must not be bitcast into a huge `u64` leaf either.
+                    if let Ok(seq) = u64::try_from(*seq) {
+                        let leaf_index = log.append(&public_key, seq, v);
+                        self.merkle_leaf_indices
+                            .insert(public_key.as_bytes().as_ref().to_vec(), leaf_index);
+                    } else {
+                        debug!(
+                            ?from,
+                            ?public_key,
+                            seq,
+                            "Rejected negative seq for Merkle log"
+                        );
+                    }
+                }
+            }
+        };
+
         // Do normal Dht request handling (peers, mutable, immutable, and routing).
         self.inner
             .handle_request(rpc, from, transaction_id, request)
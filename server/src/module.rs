@@ -0,0 +1,211 @@
+use pkarr::{mainline, PublicKey};
+
+/// Decision returned by a [RelayModule] hook: whether the relay should keep handling the
+/// request, or stop and reject it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleDecision {
+    Accept,
+    Reject(String),
+}
+
+impl ModuleDecision {
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, ModuleDecision::Accept)
+    }
+}
+
+/// A middleware hook into [crate::DhtServer] request handling, analogous to the
+/// request-body-filter and pluggable module design of modern proxy servers. Third parties
+/// implement this to ship reusable relay plugins (quota-by-key, record-type allowlists,
+/// Prometheus metrics, ...) without touching core request handling.
+///
+/// All hooks default to accepting, so a module only needs to override the ones it cares about.
+pub trait RelayModule: Send + Sync {
+    /// Called before a cached/hydrated record is served for `target`.
+    fn on_get(&self, _target: &mainline::Id) -> ModuleDecision {
+        ModuleDecision::Accept
+    }
+
+    /// Called before an incoming put is cached or republished.
+    fn on_put(&self, _public_key: &PublicKey, _seq: i64, _v: &[u8]) -> ModuleDecision {
+        ModuleDecision::Accept
+    }
+
+    /// Called with an incoming put request's `v` field, after the KRPC message has already been
+    /// decoded and `k`/`seq`/`v` split out of it, but before [RelayModule::on_put] runs. Useful
+    /// for reject/size-limit checks on the value alone that don't need the public key or
+    /// sequence number. The raw, still-bencoded put body isn't available here: by the time
+    /// [crate::DhtServer] sees the request, `mainline`'s own KRPC decoding has already consumed
+    /// it.
+    fn on_body(&self, _v: &[u8]) -> ModuleDecision {
+        ModuleDecision::Accept
+    }
+}
+
+/// Run every module's [RelayModule::on_get] hook, short-circuiting on the first rejection.
+pub(crate) fn run_on_get(
+    modules: &[Box<dyn RelayModule>],
+    target: &mainline::Id,
+) -> ModuleDecision {
+    for module in modules {
+        let decision = module.on_get(target);
+        if !decision.is_accepted() {
+            return decision;
+        }
+    }
+    ModuleDecision::Accept
+}
+
+/// Run every module's [RelayModule::on_put] hook, short-circuiting on the first rejection.
+pub(crate) fn run_on_put(
+    modules: &[Box<dyn RelayModule>],
+    public_key: &PublicKey,
+    seq: i64,
+    v: &[u8],
+) -> ModuleDecision {
+    for module in modules {
+        let decision = module.on_put(public_key, seq, v);
+        if !decision.is_accepted() {
+            return decision;
+        }
+    }
+    ModuleDecision::Accept
+}
+
+/// Run every module's [RelayModule::on_body] hook, short-circuiting on the first rejection.
+pub(crate) fn run_on_body(modules: &[Box<dyn RelayModule>], v: &[u8]) -> ModuleDecision {
+    for module in modules {
+        let decision = module.on_body(v);
+        if !decision.is_accepted() {
+            return decision;
+        }
+    }
+    ModuleDecision::Accept
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use pkarr::Keypair;
+
+    use super::*;
+
+    /// A module that records how many times each hook ran in a shared counter, and either
+    /// accepts or always rejects, so tests can assert later modules never run once an earlier
+    /// one has rejected.
+    struct CountingModule {
+        rejects: bool,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl CountingModule {
+        fn new(rejects: bool) -> (Box<dyn RelayModule>, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            (
+                Box::new(Self {
+                    rejects,
+                    calls: calls.clone(),
+                }),
+                calls,
+            )
+        }
+
+        fn decide(&self) -> ModuleDecision {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.rejects {
+                ModuleDecision::Reject("rejected".to_string())
+            } else {
+                ModuleDecision::Accept
+            }
+        }
+    }
+
+    impl RelayModule for CountingModule {
+        fn on_get(&self, _target: &mainline::Id) -> ModuleDecision {
+            self.decide()
+        }
+
+        fn on_put(&self, _public_key: &PublicKey, _seq: i64, _v: &[u8]) -> ModuleDecision {
+            self.decide()
+        }
+
+        fn on_body(&self, _v: &[u8]) -> ModuleDecision {
+            self.decide()
+        }
+    }
+
+    #[test]
+    fn run_on_get_accepts_when_every_module_accepts() {
+        let (first, _) = CountingModule::new(false);
+        let (second, _) = CountingModule::new(false);
+        let modules = vec![first, second];
+        let target = mainline::Id::random();
+
+        assert_eq!(run_on_get(&modules, &target), ModuleDecision::Accept);
+    }
+
+    #[test]
+    fn run_on_get_short_circuits_on_first_rejection() {
+        let (first, first_calls) = CountingModule::new(true);
+        let (second, second_calls) = CountingModule::new(false);
+        let modules = vec![first, second];
+        let target = mainline::Id::random();
+
+        let decision = run_on_get(&modules, &target);
+
+        assert!(!decision.is_accepted());
+        assert_eq!(first_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(second_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn run_on_put_accepts_when_every_module_accepts() {
+        let (module, _) = CountingModule::new(false);
+        let modules = vec![module];
+        let keypair = Keypair::random();
+
+        assert_eq!(
+            run_on_put(&modules, &keypair.public_key(), 1, b"v"),
+            ModuleDecision::Accept
+        );
+    }
+
+    #[test]
+    fn run_on_put_short_circuits_on_first_rejection() {
+        let (first, first_calls) = CountingModule::new(true);
+        let (second, second_calls) = CountingModule::new(false);
+        let modules = vec![first, second];
+        let keypair = Keypair::random();
+
+        let decision = run_on_put(&modules, &keypair.public_key(), 1, b"v");
+
+        assert!(!decision.is_accepted());
+        assert_eq!(first_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(second_calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn run_on_body_accepts_when_every_module_accepts() {
+        let (module, _) = CountingModule::new(false);
+        let modules = vec![module];
+
+        assert_eq!(run_on_body(&modules, b"v"), ModuleDecision::Accept);
+    }
+
+    #[test]
+    fn run_on_body_short_circuits_on_first_rejection() {
+        let (first, first_calls) = CountingModule::new(true);
+        let (second, second_calls) = CountingModule::new(false);
+        let modules = vec![first, second];
+
+        let decision = run_on_body(&modules, b"v");
+
+        assert!(!decision.is_accepted());
+        assert_eq!(first_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(second_calls.load(Ordering::SeqCst), 0);
+    }
+}